@@ -0,0 +1,213 @@
+use anyhow::anyhow;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+/// Path to the cache file that records every repo path under the repos directory.
+/// Honors `REPO_CACHE_FILE` so tests (and advanced users) can redirect it.
+fn cache_file() -> anyhow::Result<PathBuf> {
+    if let Some(path) = std::env::var_os("REPO_CACHE_FILE") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let cache_dir = dirs::cache_dir().ok_or_else(|| anyhow!("Could not find user cache directory"))?;
+    Ok(cache_dir.join("repo").join("cache"))
+}
+
+/// Recursively find every repo (a directory containing `.git`) under `root`,
+/// regardless of how deeply it's nested, without descending into repos once found.
+pub fn find_repos(root: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut repos = Vec::new();
+    let mut entries = walkdir::WalkDir::new(root).min_depth(1).into_iter();
+
+    while let Some(entry) = entries.next() {
+        let entry = entry?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+
+        if entry.path().join(".git").is_dir() {
+            repos.push(entry.path().to_path_buf());
+            entries.skip_current_dir();
+        }
+    }
+
+    Ok(repos)
+}
+
+/// Walk `repos_dir` and rewrite the cache file with every repo path found.
+pub fn reindex(repos_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let repos = find_repos(repos_dir)?;
+
+    let cache_file = cache_file()?;
+    if let Some(parent) = cache_file.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut file = std::fs::File::create(&cache_file)?;
+    for repo in &repos {
+        writeln!(file, "{}", repo.display())?;
+    }
+
+    Ok(repos)
+}
+
+/// Load the cached repo paths, rebuilding the cache first if it doesn't exist yet.
+pub fn load(repos_dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let cache_file = cache_file()?;
+    if !cache_file.is_file() {
+        return reindex(repos_dir);
+    }
+
+    let file = std::fs::File::open(&cache_file)?;
+    let reader = std::io::BufReader::new(file);
+    reader
+        .lines()
+        .map(|line| Ok(PathBuf::from(line?)))
+        .collect()
+}
+
+/// Cached repos whose final path component matches `name`.
+fn matching<'a>(repos: &'a [PathBuf], name: &str) -> Vec<&'a PathBuf> {
+    repos
+        .iter()
+        .filter(|path| path.file_name().map(|n| n == name).unwrap_or(false))
+        .collect()
+}
+
+/// Parse a 1-based choice typed by the user into a 0-based index into `len` candidates.
+fn parse_choice(choice: &str, len: usize) -> anyhow::Result<usize> {
+    let idx: usize = choice.parse().map_err(|_| anyhow!("invalid choice"))?;
+    idx.checked_sub(1)
+        .filter(|idx| *idx < len)
+        .ok_or_else(|| anyhow!("invalid choice"))
+}
+
+/// Find the cached repo whose final path component matches `name`, prompting the
+/// user to disambiguate on stderr when more than one candidate matches.
+pub fn find_interactive(repos_dir: &Path, name: &str) -> anyhow::Result<PathBuf> {
+    let repos = load(repos_dir)?;
+    let candidates = matching(&repos, name);
+
+    match candidates.len() {
+        0 => Err(anyhow!("no such directory")),
+        1 => Ok(candidates[0].clone()),
+        _ => {
+            for (idx, path) in candidates.iter().enumerate() {
+                eprintln!("[{}] {}", idx + 1, path.display());
+            }
+            eprint!("Enter your choice: ");
+            std::io::stderr().flush()?;
+
+            let mut choice = String::new();
+            std::io::stdin().read_line(&mut choice)?;
+            let idx = parse_choice(choice.trim(), candidates.len())?;
+
+            Ok(candidates[idx].clone())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// `reindex`/`load` tests redirect `REPO_CACHE_FILE` into a tempdir; serialize
+    /// them so they don't stomp on each other's environment variable.
+    static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("repo-cache-test-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn make_repo(path: &Path) {
+        std::fs::create_dir_all(path.join(".git")).unwrap();
+    }
+
+    #[test]
+    fn test_find_repos_nested_layouts() {
+        let root = temp_dir("find-repos");
+
+        let shallow = root.join("github.com").join("rust-lang").join("rust");
+        make_repo(&shallow);
+
+        let nested = root.join("gitlab.com").join("group").join("subgroup").join("repo");
+        make_repo(&nested);
+
+        let mut repos = find_repos(&root).unwrap();
+        repos.sort();
+
+        let mut expected = vec![shallow, nested];
+        expected.sort();
+
+        assert_eq!(repos, expected);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_find_repos_does_not_descend_into_found_repos() {
+        let root = temp_dir("no-descend");
+
+        let repo = root.join("host").join("user").join("repo");
+        make_repo(&repo);
+        std::fs::create_dir_all(repo.join("vendor").join("nested").join(".git")).unwrap();
+
+        let repos = find_repos(&root).unwrap();
+        assert_eq!(repos, vec![repo]);
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_reindex_and_load_roundtrip() {
+        let _guard = ENV_LOCK.lock().unwrap();
+
+        let repos_dir = temp_dir("reindex-roundtrip");
+        let repo = repos_dir.join("host").join("user").join("repo");
+        make_repo(&repo);
+
+        let cache_file = repos_dir.join("cache-file");
+        std::env::set_var("REPO_CACHE_FILE", &cache_file);
+
+        let indexed = reindex(&repos_dir).unwrap();
+        assert_eq!(indexed, vec![repo.clone()]);
+        assert!(cache_file.is_file());
+
+        let loaded = load(&repos_dir).unwrap();
+        assert_eq!(loaded, vec![repo]);
+
+        std::env::remove_var("REPO_CACHE_FILE");
+        std::fs::remove_dir_all(&repos_dir).unwrap();
+    }
+
+    #[test]
+    fn test_matching_filters_by_final_component() {
+        let repos = vec![
+            PathBuf::from("/repos/github.com/a/foo"),
+            PathBuf::from("/repos/gitlab.com/b/foo"),
+            PathBuf::from("/repos/github.com/a/bar"),
+        ];
+
+        let found = matching(&repos, "foo");
+        assert_eq!(found.len(), 2);
+
+        let found = matching(&repos, "bar");
+        assert_eq!(found, vec![&repos[2]]);
+
+        let found = matching(&repos, "missing");
+        assert!(found.is_empty());
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("1", 3).unwrap(), 0);
+        assert_eq!(parse_choice("3", 3).unwrap(), 2);
+        assert!(parse_choice("0", 3).is_err());
+        assert!(parse_choice("4", 3).is_err());
+        assert!(parse_choice("not a number", 3).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use anyhow::anyhow;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+/// A declarative list of repos tracked in `~/.config/repo/repos.toml`, so that a
+/// machine's full checkout can be reproduced with `repo sync`.
+#[derive(Deserialize, Debug, Default)]
+pub struct Manifest {
+    #[serde(default, rename = "repos")]
+    pub repos: Vec<RepoEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RepoEntry {
+    pub url: String,
+    pub category: Option<String>,
+    #[serde(default)]
+    pub clone: bool,
+    #[serde(default)]
+    pub pull: bool,
+    #[serde(default)]
+    pub fast: bool,
+}
+
+fn manifest_path() -> anyhow::Result<PathBuf> {
+    let config_dir = dirs::config_dir().ok_or_else(|| anyhow!("Could not find user config directory"))?;
+    Ok(config_dir.join("repo").join("repos.toml"))
+}
+
+pub fn load() -> anyhow::Result<Manifest> {
+    let manifest_path = manifest_path()?;
+    let contents = std::fs::read_to_string(&manifest_path)
+        .map_err(|e| anyhow!("Could not read {}: {e}", manifest_path.display()))?;
+
+    parse(&contents).map_err(|e| anyhow!("Could not parse {}: {e}", manifest_path.display()))
+}
+
+fn parse(contents: &str) -> anyhow::Result<Manifest> {
+    Ok(toml::from_str(contents)?)
+}
+
+#[test]
+fn test_parse_manifest() {
+    let manifest = parse(
+        r#"
+            [[repos]]
+            url = "https://github.com/rust-lang/rust"
+            category = "oss"
+            clone = true
+            pull = true
+
+            [[repos]]
+            url = "git@github.com:me/dotfiles.git"
+            fast = true
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(manifest.repos.len(), 2);
+
+    let rust = &manifest.repos[0];
+    assert_eq!(rust.url, "https://github.com/rust-lang/rust");
+    assert_eq!(rust.category.as_deref(), Some("oss"));
+    assert!(rust.clone);
+    assert!(rust.pull);
+    assert!(!rust.fast);
+
+    let dotfiles = &manifest.repos[1];
+    assert_eq!(dotfiles.url, "git@github.com:me/dotfiles.git");
+    assert_eq!(dotfiles.category, None);
+    assert!(!dotfiles.clone);
+    assert!(!dotfiles.pull);
+    assert!(dotfiles.fast);
+}
+
+#[test]
+fn test_parse_empty_manifest() {
+    let manifest = parse("").unwrap();
+    assert!(manifest.repos.is_empty());
+}
+
+#[test]
+fn test_parse_manifest_rejects_missing_url() {
+    assert!(parse("[[repos]]\ncategory = \"oss\"\n").is_err());
+}
@@ -4,28 +4,109 @@ use clap::{Parser, Subcommand, Args};
 use std::path::PathBuf;
 use std::process::{Command, Stdio};
 
+mod cache;
+mod config;
+
 
 #[derive(Parser, Debug)]
 #[command(name="repo")]
 #[command(bin_name="repo")]
 enum Cli {
     Clone(CloneArgs),
-    FetchAll,
+    FetchAll(FetchAllArgs),
+    Cd(CdArgs),
+    Reindex,
+    Sync(SyncArgs),
 }
 
 #[derive(Args, Debug)]
 struct CloneArgs {
     uri: String,
-    #[clap(short = 'l')]
+    #[clap(short = 'l', long = "link")]
     link: bool,
 }
 
+#[derive(Args, Debug)]
+struct FetchAllArgs {
+    /// Number of repos to update concurrently (defaults to available parallelism)
+    #[clap(short = 'j', long = "jobs")]
+    jobs: Option<usize>,
+    /// Fast-forward the checked-out branch after fetching, when possible
+    #[clap(long = "pull")]
+    pull: bool,
+}
+
+#[derive(Args, Debug)]
+struct CdArgs {
+    name: String,
+}
+
+#[derive(Args, Debug)]
+struct SyncArgs {
+    /// Only sync repos tagged with this category in repos.toml
+    #[clap(long = "category")]
+    category: Option<String>,
+}
+
 fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
     match args {
         Cli::Clone(args) => do_clone(args),
-        Cli::FetchAll => do_fetch_all(),
+        Cli::FetchAll(args) => do_fetch_all(args),
+        Cli::Cd(args) => do_cd(args),
+        Cli::Reindex => do_reindex(),
+        Cli::Sync(args) => do_sync(args),
+    }
+}
+
+fn do_cd(args: CdArgs) -> anyhow::Result<()> {
+    let repos_dir = repos_dir()?;
+    let path = cache::find_interactive(&repos_dir, &args.name)?;
+    println!("{}", path.display());
+    Ok(())
+}
+
+fn do_reindex() -> anyhow::Result<()> {
+    let repos_dir = repos_dir()?;
+    let repos = cache::reindex(&repos_dir)?;
+    println!("Indexed {} repo(s)", repos.len());
+    Ok(())
+}
+
+fn do_sync(args: SyncArgs) -> anyhow::Result<()> {
+    let repos_dir = repos_dir()?;
+    let manifest = config::load()?;
+
+    for entry in &manifest.repos {
+        if args.category.is_some() && entry.category != args.category {
+            continue;
+        }
+
+        if let Err(e) = sync_one(&repos_dir, entry) {
+            eprintln!("\x1b[31merror\x1b[0m  {}: {e}", entry.url);
+        }
+    }
+
+    Ok(())
+}
+
+fn sync_one(repos_dir: &std::path::Path, entry: &config::RepoEntry) -> anyhow::Result<()> {
+    let (domain, user, repo) = parse_uri(&entry.url)?;
+    let target_dir = repos_dir.join(domain).join(user).join(repo);
+
+    if !target_dir.is_dir() {
+        if entry.clone {
+            do_clone(CloneArgs { uri: entry.url.clone(), link: false })?;
+        }
+        return Ok(());
+    }
+
+    if entry.pull || entry.fast {
+        let update = fetch_one(&target_dir, entry.pull || entry.fast);
+        print_repo_update(&target_dir, &update);
     }
+
+    Ok(())
 }
 
 fn do_clone(args: CloneArgs) -> anyhow::Result<()> {
@@ -91,29 +172,34 @@ fn do_clone(args: CloneArgs) -> anyhow::Result<()> {
     let target_dir_path = String::from_utf8_lossy(target_dir.to_str().ok_or_else(|| anyhow!("non-utf8 path"))?.as_bytes());
     println!("{target_dir_path}");
 
-    let projects_dir = projects_dir()?;
-    let link = projects_dir.join(repo);
-    let link_path = String::from_utf8_lossy(link.to_str().ok_or_else(|| anyhow!("non-utf8 path"))?.as_bytes());
-    println!("{link_path}");
+    if args.link {
+        let projects_dir = projects_dir()?;
+        let link = projects_dir.join(repo);
+        let link_path = String::from_utf8_lossy(link.to_str().ok_or_else(|| anyhow!("non-utf8 path"))?.as_bytes());
+        println!("{link_path}");
 
-    let mut child = Command::new("ln")
-        .arg("-s")
-        .arg(&*target_dir_path)
-        .arg(&*link_path)
-        .spawn()?;
+        let mut child = Command::new("ln")
+            .arg("-s")
+            .arg(&*target_dir_path)
+            .arg(&*link_path)
+            .spawn()?;
 
-    let status = child.wait()?;
+        let status = child.wait()?;
 
-    if !status.success() {
-        return Err(anyhow!("Error: Could not link: Exist status {status}"));
+        if !status.success() {
+            return Err(anyhow!("Error: Could not link: Exist status {status}"));
+        }
     }
 
     Ok(())
 }
 
 fn repos_dir() -> anyhow::Result<PathBuf> {
-    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not find user home directory"))?;
-    let repo_dir = home_dir.join("repos");
+    let repo_dir = match std::env::var_os("REPO_HOME") {
+        Some(path) => std::path::absolute(path)?,
+        None => dirs::home_dir().ok_or_else(|| anyhow!("Could not find user home directory"))?.join("repos"),
+    };
+
     if !std::fs::metadata(&repo_dir).is_ok() {
         std::fs::create_dir_all(&repo_dir)?;
     }
@@ -122,8 +208,11 @@ fn repos_dir() -> anyhow::Result<PathBuf> {
 }
 
 fn projects_dir() -> anyhow::Result<PathBuf> {
-    let home_dir = dirs::home_dir().ok_or_else(|| anyhow!("Could not find user home directory"))?;
-    let projects_dir = home_dir.join("projects");
+    let projects_dir = match std::env::var_os("REPO_LINK_DIR") {
+        Some(path) => std::path::absolute(path)?,
+        None => dirs::home_dir().ok_or_else(|| anyhow!("Could not find user home directory"))?.join("projects"),
+    };
+
     if !std::fs::metadata(&projects_dir).is_ok() {
         std::fs::create_dir_all(&projects_dir)?;
     }
@@ -131,76 +220,273 @@ fn projects_dir() -> anyhow::Result<PathBuf> {
     Ok(projects_dir)
 }
 
-fn parse_uri(uri: &str) -> anyhow::Result<(String, String, String)> {
-    if uri.starts_with("https://") {
-        let uri_no_schema = &uri[8..];
-        let Some(slash_idx) = uri_no_schema.find('/') else {
-            return Err(anyhow!("Could not parse uri: {uri:?}"));
-        };
+/// `repos_dir`/`projects_dir` tests mutate `REPO_HOME`/`REPO_LINK_DIR`; serialize
+/// them so they don't stomp on each other's environment variable.
+#[cfg(test)]
+static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
 
-        let domain = uri_no_schema[..slash_idx].to_string();
-        let rest = &uri_no_schema[slash_idx + 1..];
+#[cfg(test)]
+fn unique_temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("repo-main-test-{name}-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
 
-        let Some(slash_idx2) = rest.find('/') else {
-            return Err(anyhow!("Could not parse uri: {uri:?}"));
-        };
+#[test]
+fn test_repos_dir_honors_repo_home() {
+    let _guard = ENV_LOCK.lock().unwrap();
+    let dir = unique_temp_dir("repos-dir");
+    std::env::set_var("REPO_HOME", &dir);
 
-        let user = rest[..slash_idx2].to_string();
+    let resolved = repos_dir();
 
-        let mut repo = String::new();
-        for ch in rest[slash_idx2+1..].chars() {
-            if ch != '/' && ch != '.' {
-                repo.push(ch);
-            }
-        }
+    std::env::remove_var("REPO_HOME");
+    let resolved = resolved.unwrap();
+    assert_eq!(resolved, dir);
+    assert!(resolved.is_dir());
 
-        Ok((domain, user, repo))
-    } else if uri.starts_with("git@") {
-        let uri_no_schema = &uri[4..];
-        let Some(slash_idx) = uri_no_schema.find(':') else {
-            return Err(anyhow!("Could not parse uri: {uri:?}"));
-        };
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_repos_dir_resolves_relative_repo_home_to_absolute() {
+    let _guard = ENV_LOCK.lock().unwrap();
+    std::env::set_var("REPO_HOME", "repo-main-test-relative-repos-dir");
+
+    let resolved = repos_dir();
 
-        let domain = uri_no_schema[..slash_idx].to_string();
-        let rest = &uri_no_schema[slash_idx + 1..];
+    std::env::remove_var("REPO_HOME");
+    let resolved = resolved.unwrap();
+    assert!(resolved.is_absolute());
 
-        let Some(slash_idx2) = rest.find('/') else {
+    std::fs::remove_dir_all(&resolved).unwrap();
+}
+
+#[test]
+fn test_projects_dir_honors_repo_link_dir() {
+    let _guard = ENV_LOCK.lock().unwrap();
+    let dir = unique_temp_dir("projects-dir");
+    std::env::set_var("REPO_LINK_DIR", &dir);
+
+    let resolved = projects_dir();
+
+    std::env::remove_var("REPO_LINK_DIR");
+    let resolved = resolved.unwrap();
+    assert_eq!(resolved, dir);
+    assert!(resolved.is_dir());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+fn parse_uri(uri: &str) -> anyhow::Result<(String, String, String)> {
+    if uri.contains("://") {
+        let url = Url::parse(uri).map_err(|_| anyhow!("Could not parse uri: {uri:?}"))?;
+
+        let domain = url
+            .host_str()
+            .ok_or_else(|| anyhow!("Could not parse uri: {uri:?}"))?
+            .to_string();
+
+        let segments: Vec<&str> = url
+            .path_segments()
+            .ok_or_else(|| anyhow!("Could not parse uri: {uri:?}"))?
+            .filter(|s| !s.is_empty())
+            .collect();
+
+        path_to_user_repo(&segments, uri).map(|(user, repo)| (domain, user, repo))
+    } else {
+        let Some(colon_idx) = uri.find(':') else {
             return Err(anyhow!("Could not parse uri: {uri:?}"));
         };
 
-        let user = rest[..slash_idx2].to_string();
+        let host_part = &uri[..colon_idx];
+        let path_part = &uri[colon_idx + 1..];
 
-        let mut repo = String::new();
-        for ch in rest[slash_idx2+1..].chars() {
-            if ch != '/' && ch != '.' {
-                repo.push(ch);
-            } else {
-                break;
-            }
-        }
+        let domain = host_part.rsplit('@').next().unwrap_or(host_part).to_string();
 
-        Ok((domain, user, repo))
-    } else {
-        Err(anyhow!("Could not parse uri: {uri:?}"))
+        let segments: Vec<&str> = path_part.split('/').filter(|s| !s.is_empty()).collect();
+
+        path_to_user_repo(&segments, uri).map(|(user, repo)| (domain, user, repo))
     }
 }
 
+/// Split a URI's path segments into (user/namespace, repo), treating everything
+/// before the final segment as the (possibly nested) user/namespace directory
+/// and stripping a trailing `.git` suffix from the repo name.
+fn path_to_user_repo(segments: &[&str], uri: &str) -> anyhow::Result<(String, String)> {
+    let Some((repo, namespace)) = segments.split_last() else {
+        return Err(anyhow!("Could not parse uri: {uri:?}"));
+    };
+
+    if namespace.is_empty() {
+        return Err(anyhow!("Could not parse uri: {uri:?}"));
+    }
+
+    let repo = repo.strip_suffix(".git").unwrap_or(repo).to_string();
+    let user = namespace.join("/");
+
+    Ok((user, repo))
+}
+
 #[test]
 fn test_parse_uri() {
-    let (domain, group, repo) = parse_uri("https://github.com/rust-lang/rust/").unwrap();
-    assert_eq!((domain.as_str(), group.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+    let (domain, user, repo) = parse_uri("https://github.com/rust-lang/rust/").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+
+    let (domain, user, repo) = parse_uri("https://github.com/rust-lang/rust.git").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+
+    let (domain, user, repo) = parse_uri("https://github.com/rust-lang/rust.fork").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust.fork"));
+
+    let (domain, user, repo) = parse_uri("ssh://git@github.com/rust-lang/rust.git").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+
+    let (domain, user, repo) = parse_uri("git://github.com/rust-lang/rust.git").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
 
-    let (domain, group, repo) = parse_uri("git@github.com:rust-lang/rust").unwrap();
-    assert_eq!((domain.as_str(), group.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+    let (domain, user, repo) = parse_uri("git@github.com:rust-lang/rust").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
 
-    let (domain, group, repo) = parse_uri("git@github.com:rust-lang/rust.git").unwrap();
-    assert_eq!((domain.as_str(), group.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+    let (domain, user, repo) = parse_uri("git@github.com:rust-lang/rust.git").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("github.com", "rust-lang", "rust"));
+
+    let (domain, user, repo) = parse_uri("https://gitlab.com/group/subgroup/repo").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("gitlab.com", "group/subgroup", "repo"));
+
+    let (domain, user, repo) = parse_uri("git@gitlab.com:group/subgroup/repo.git").unwrap();
+    assert_eq!((domain.as_str(), user.as_str(), repo.as_str()), ("gitlab.com", "group/subgroup", "repo"));
+}
+
+enum RepoUpdate {
+    UpToDate,
+    Behind(u32),
+    FastForwarded(u32),
+    Diverged,
+    CheckFailed(String),
+    FetchFailed(String),
 }
 
-fn do_fetch_all() -> anyhow::Result<()> {
+fn do_fetch_all(args: FetchAllArgs) -> anyhow::Result<()> {
     let repos_dir = repos_dir()?;
-    for entry in walkdir::WalkDir::new(repos_dir).min_depth(2).max_depth(2) {
-        println!("{entry:?}");
+    let repos = cache::find_repos(&repos_dir)?;
+
+    let jobs = args.jobs.unwrap_or_else(|| {
+        std::thread::available_parallelism().map(|n| n.get()).unwrap_or(1)
+    });
+
+    let work = std::sync::Arc::new(std::sync::Mutex::new(repos));
+    let pull = args.pull;
+
+    let handles: Vec<_> = (0..jobs)
+        .map(|_| {
+            let work = std::sync::Arc::clone(&work);
+            std::thread::spawn(move || {
+                let mut results = Vec::new();
+                loop {
+                    let repo = work.lock().unwrap().pop();
+                    let Some(repo) = repo else { break };
+                    let update = fetch_one(&repo, pull);
+                    results.push((repo, update));
+                }
+                results
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        for (repo, update) in handle.join().expect("worker thread panicked") {
+            print_repo_update(&repo, &update);
+        }
     }
+
     Ok(())
 }
+
+fn fetch_one(repo: &std::path::Path, pull: bool) -> RepoUpdate {
+    let fetch = Command::new("git")
+        .arg("fetch")
+        .arg("--quiet")
+        .current_dir(repo)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output();
+
+    let fetch = match fetch {
+        Ok(fetch) => fetch,
+        Err(e) => return RepoUpdate::FetchFailed(e.to_string()),
+    };
+    if !fetch.status.success() {
+        return RepoUpdate::FetchFailed(stderr_text(&fetch.stderr));
+    }
+
+    let behind = match rev_list_count(repo, "HEAD..@{u}") {
+        Ok(n) => n,
+        Err(e) => return RepoUpdate::CheckFailed(e.to_string()),
+    };
+    let ahead = match rev_list_count(repo, "@{u}..HEAD") {
+        Ok(n) => n,
+        Err(e) => return RepoUpdate::CheckFailed(e.to_string()),
+    };
+
+    if behind == 0 {
+        return RepoUpdate::UpToDate;
+    }
+
+    if ahead > 0 {
+        return RepoUpdate::Diverged;
+    }
+
+    if pull {
+        let pull = Command::new("git")
+            .arg("pull")
+            .arg("--ff-only")
+            .arg("--quiet")
+            .current_dir(repo)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+
+        match pull {
+            Ok(pull) if pull.status.success() => RepoUpdate::FastForwarded(behind),
+            Ok(pull) => RepoUpdate::FetchFailed(stderr_text(&pull.stderr)),
+            Err(e) => RepoUpdate::FetchFailed(e.to_string()),
+        }
+    } else {
+        RepoUpdate::Behind(behind)
+    }
+}
+
+fn stderr_text(stderr: &[u8]) -> String {
+    String::from_utf8_lossy(stderr).trim().to_string()
+}
+
+fn rev_list_count(repo: &std::path::Path, range: &str) -> anyhow::Result<u32> {
+    let output = Command::new("git")
+        .arg("rev-list")
+        .arg("--count")
+        .arg(range)
+        .current_dir(repo)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(anyhow!("git rev-list --count {range} failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|e| anyhow!("could not parse rev-list output: {e}"))
+}
+
+fn print_repo_update(repo: &std::path::Path, update: &RepoUpdate) {
+    let name = repo.display();
+    match update {
+        RepoUpdate::UpToDate => println!("\x1b[32mup-to-date\x1b[0m  {name}"),
+        RepoUpdate::Behind(n) => println!("\x1b[33mbehind {n} commit(s)\x1b[0m  {name}"),
+        RepoUpdate::FastForwarded(n) => println!("\x1b[36mfast-forwarded {n} commit(s)\x1b[0m  {name}"),
+        RepoUpdate::Diverged => println!("\x1b[33mdiverged\x1b[0m  {name}"),
+        RepoUpdate::CheckFailed(msg) => println!("\x1b[31mcould not compare with upstream\x1b[0m  {name}\n  {msg}"),
+        RepoUpdate::FetchFailed(msg) => println!("\x1b[31mfetch failed\x1b[0m  {name}\n  {msg}"),
+    }
+}